@@ -1,15 +1,18 @@
+use std::convert::TryFrom;
 use std::ffi::{CString, CStr};
 use std::process::exit;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
 
 use clap::{App, Arg, crate_version};
 
 use nix::mount::{mount, MsFlags};
 use nix::sched::unshare;
 use nix::sched::CloneFlags;
-use nix::sys::wait::{wait, WaitStatus};
+use nix::sys::signal::{kill, sigaction, SigAction, SaFlags, SigHandler, SigSet, Signal};
+use nix::sys::wait::{waitpid, WaitStatus};
 use nix::sys::stat::{mknod, makedev, Mode, SFlag};
-use nix::unistd::{chdir, chroot, execve, fork, geteuid, symlinkat, ForkResult};
+use nix::unistd::{chdir, chroot, execve, fork, geteuid, getegid, symlinkat, ForkResult, Group, Pid, Uid, Gid};
 
 const DEFAULT_EXEC: &str = "/bin/sh";
 const PATH: &str = "PATH=/usr/sbin:/usr/bin:/sbin:/bin";
@@ -20,11 +23,27 @@ fn main() {
         .about("Suped up chroot using namespaces")
         .version(crate_version!())
         .arg(Arg::with_name("readonly").short("R").long("readonly").help("Mount filesystem readonly"))
+        .arg(Arg::with_name("rootless").short("u").long("rootless").alias("user").help("Run without root privileges using a user namespace"))
+        .arg(Arg::with_name("propagation").long("propagation").takes_value(true)
+            .possible_values(&["shared", "private", "slave", "unbindable"]).default_value("slave")
+            .help("Mount propagation to apply to the root before entering the jail"))
+        .arg(Arg::with_name("bind").long("bind").takes_value(true).number_of_values(1).multiple(true)
+            .help("Bind-mount SRC:DST[:ro] into the jail (can be repeated)"))
+        .arg(Arg::with_name("tmpfs").long("tmpfs").takes_value(true).number_of_values(1).multiple(true)
+            .help("Mount a tmpfs at DST[:opts] in the jail (can be repeated)"))
+        .arg(Arg::with_name("overlay").long("overlay").takes_value(true).value_name("UPPER:WORK")
+            .help("Mount ROOT as a copy-on-write overlayfs with the given upper/work dirs"))
+        .arg(Arg::with_name("lower").long("lower").takes_value(true).number_of_values(1).multiple(true)
+            .help("Additional overlayfs lower directory, below ROOT (can be repeated; without --overlay, ROOT is mounted as a read-only overlay)"))
+        .arg(Arg::with_name("image").long("image").help("Treat ROOT as a filesystem image file, attached via a loop device"))
+        .arg(Arg::with_name("fs-type").long("fs-type").takes_value(true).requires("image")
+            .help("Filesystem type of --image (default: autodetect)"))
         .arg(Arg::with_name("ROOT").required(true).index(1).help("Filesystem root"))
         .arg(Arg::with_name("ARG").multiple(true).last(true).help("Command arguments"))
         .get_matches();
 
-    if !geteuid().is_root() {
+    let rootless = matches.is_present("rootless");
+    if !rootless && !geteuid().is_root() {
         eprintln!("Must be run as root");
         exit(1);
     }
@@ -34,64 +53,364 @@ fn main() {
         .map(|args| args.map(|arg| CString::new(arg).unwrap()).collect::<Vec<_>>())
         .unwrap_or_else(|| vec![CString::new(DEFAULT_EXEC).unwrap()]);
 
-    unshare_namespaces();
+    let real_euid = geteuid();
+    let real_egid = getegid();
+
+    unshare_namespaces(rootless);
+    if rootless {
+        setup_id_maps(real_euid, real_egid);
+    }
+    let propagation = propagation_flags(matches.value_of("propagation").unwrap());
+    let binds: Vec<BindMount> = matches.values_of("bind")
+        .map(|specs| specs.map(parse_bind_spec).collect())
+        .unwrap_or_default();
+    let tmpfs_mounts: Vec<TmpfsMount> = matches.values_of("tmpfs")
+        .map(|specs| specs.map(parse_tmpfs_spec).collect())
+        .unwrap_or_default();
+    let lowers: Vec<PathBuf> = matches.values_of("lower")
+        .map(|dirs| dirs.map(PathBuf::from).collect())
+        .unwrap_or_default();
+    let overlay = if matches.is_present("overlay") || !lowers.is_empty() {
+        let upper_work = matches.value_of("overlay").map(parse_overlay_upper_work);
+        Some(OverlaySpec { lowers, upper_work })
+    } else {
+        None
+    };
+
     fork_and_supervise();
-    setup_mounts(&target, matches.is_present("readonly"));
-    setup_devices(&target);
+    let target = if matches.is_present("image") {
+        mount_image(&target, matches.value_of("fs-type"), matches.is_present("readonly"))
+    } else {
+        target
+    };
+    setup_mounts(&target, matches.is_present("readonly"), propagation, overlay.as_ref(), &binds, &tmpfs_mounts, rootless);
+    setup_devices(&target, rootless);
     enter_chroot(&target, &args);
 }
 
 /// Unshare namespaces
-fn unshare_namespaces() {
-    let flags = CloneFlags::CLONE_NEWNS
+fn unshare_namespaces(rootless: bool) {
+    let mut flags = CloneFlags::CLONE_NEWNS
         | CloneFlags::CLONE_NEWPID
         | CloneFlags::CLONE_NEWIPC
         | CloneFlags::CLONE_NEWUTS;
+    if rootless {
+        flags |= CloneFlags::CLONE_NEWUSER;
+    }
     unshare(flags).expect("Unshare failed");
 }
 
-/// Fork and have parent supervise child
+/// Establish the uid/gid identity maps for a fresh user namespace.
+///
+/// Must run immediately after `unshare(CLONE_NEWUSER)` and before any other
+/// privileged setup, as these files may only be written once and root-in-namespace
+/// is only available once the map is installed.
+fn setup_id_maps(real_euid: Uid, real_egid: Gid) {
+    std::fs::write("/proc/self/setgroups", "deny").expect("Failed to deny setgroups");
+    std::fs::write("/proc/self/uid_map", format!("0 {} 1", real_euid)).expect("Failed to write uid_map");
+    std::fs::write("/proc/self/gid_map", format!("0 {} 1", real_egid)).expect("Failed to write gid_map");
+}
+
+/// Pid of the child being supervised, shared with the signal handler
+static SUPERVISED_CHILD: AtomicI32 = AtomicI32::new(0);
+
+/// Fork and have the parent supervise the child, relaying termination signals
+/// into it and its eventual exit status/signal back out to our own exit.
+///
+/// `unshare(CLONE_NEWPID)` only affects this process's *future* children: this
+/// fork's child becomes PID 1 of the new PID namespace, while we stay behind in
+/// the outer one. Reaping reparented orphans is therefore not our job here (we
+/// only ever have this one child) — that happens inside `run_as_init`, which
+/// forks again from within the new namespace so PID 1 can stay behind as the
+/// reaper while its child goes on to run the jailed command.
 fn fork_and_supervise() {
     match unsafe { fork().expect("Fork failed") } {
-        ForkResult::Parent { .. } => {
-            // Wait for child to exit
-            match wait().expect("Wait failed") {
-                WaitStatus::Exited(_, exitcode) => exit(exitcode),
-                _ => exit(1),
-            }
-        },
+        ForkResult::Parent { child } => supervise(child),
+        ForkResult::Child => run_as_init(),
+    }
+}
+
+/// Run as PID 1 of the new PID namespace: fork once more so our child can go on
+/// to set up and exec the jailed command, while we stay behind to reap
+/// reparented orphans and relay signals/exit status into and out of our subtree.
+fn run_as_init() {
+    match unsafe { fork().expect("Fork failed") } {
+        ForkResult::Parent { child } => supervise(child),
         ForkResult::Child => (),
     }
 }
 
+/// Forward termination signals to `child`, reap until it exits (ignoring any
+/// other reparented processes reaped along the way), then relay its fate
+/// faithfully so the caller's `$?` reflects what actually happened
+fn supervise(child: Pid) -> ! {
+    SUPERVISED_CHILD.store(child.as_raw(), Ordering::SeqCst);
+    install_signal_forwarding();
+
+    loop {
+        match waitpid(Pid::from_raw(-1), None) {
+            Ok(WaitStatus::Exited(pid, exitcode)) if pid == child => exit(exitcode),
+            // Re-raising the signal on ourselves isn't reliable here: SIGKILL/SIGSTOP's
+            // disposition can never be changed, and as PID 1 of our namespace a self-raised
+            // signal reset to SIG_DFL is simply discarded rather than acted on. Exit with
+            // the shell's `128 + signal` convention instead.
+            Ok(WaitStatus::Signaled(pid, sig, _)) if pid == child => exit(128 + sig as i32),
+            Ok(_) => continue,
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(nix::errno::Errno::ECHILD) => exit(1),
+            Err(err) => panic!("Wait failed: {}", err),
+        }
+    }
+}
+
+/// Install handlers for the common termination signals that relay them to the
+/// supervised child, so Ctrl-C and `kill` against choot reach the jailed init
+fn install_signal_forwarding() {
+    let action = SigAction::new(SigHandler::Handler(forward_signal), SaFlags::empty(), SigSet::empty());
+    for sig in [Signal::SIGTERM, Signal::SIGINT, Signal::SIGHUP, Signal::SIGQUIT] {
+        unsafe { sigaction(sig, &action) }.expect("Failed to install signal handler");
+    }
+}
+
+extern "C" fn forward_signal(signum: nix::libc::c_int) {
+    let child = SUPERVISED_CHILD.load(Ordering::SeqCst);
+    if child != 0 {
+        let sig = Signal::try_from(signum).expect("Unexpected signal number");
+        let _ = kill(Pid::from_raw(child), sig);
+    }
+}
+
+const LO_FLAGS_READ_ONLY: u32 = 1;
+const LO_FLAGS_AUTOCLEAR: u32 = 4;
+
+/// Mirrors `struct loop_info64` from `<linux/loop.h>`
+#[repr(C)]
+struct LoopInfo64 {
+    lo_device: u64,
+    lo_inode: u64,
+    lo_rdevice: u64,
+    lo_offset: u64,
+    lo_sizelimit: u64,
+    lo_number: u32,
+    lo_encrypt_type: u32,
+    lo_encrypt_key_size: u32,
+    lo_flags: u32,
+    lo_file_name: [u8; 64],
+    lo_crypt_name: [u8; 64],
+    lo_encrypt_key: [u8; 32],
+    lo_init: [u64; 2],
+}
+
+impl Default for LoopInfo64 {
+    fn default() -> Self {
+        // The byte arrays here are larger than the stable `Default` impl for
+        // arrays covers, so zero the struct directly instead.
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+// LOOP_CTL_GET_FREE/LOOP_SET_FD/LOOP_SET_STATUS64 are legacy ioctls: <linux/loop.h>
+// defines them as raw magic numbers, not via the standard _IOC direction/size
+// encoding, so they must go through the `_bad` macros rather than being
+// reconstructed from a (type, nr) pair.
+nix::ioctl_none_bad!(loop_ctl_get_free, 0x4C82);
+nix::ioctl_write_int_bad!(loop_set_fd, 0x4C00);
+nix::ioctl_write_ptr_bad!(loop_set_status64, 0x4C04, LoopInfo64);
+
+/// Attach `image` to a free loop device, returning its `/dev/loopN` path
+fn attach_loop_device<T: AsRef<Path>>(image: T, readonly: bool) -> PathBuf {
+    use std::os::unix::io::AsRawFd;
+
+    let ctl = std::fs::File::open("/dev/loop-control").expect("Failed to open /dev/loop-control");
+    let minor = unsafe { loop_ctl_get_free(ctl.as_raw_fd()) }.expect("Failed to get free loop device");
+
+    let loop_path = PathBuf::from(format!("/dev/loop{}", minor));
+    let loop_dev = std::fs::OpenOptions::new().read(true).write(true).open(&loop_path).expect("Failed to open loop device");
+    let image_file = std::fs::OpenOptions::new().read(true).write(!readonly).open(image.as_ref()).expect("Failed to open image");
+
+    unsafe { loop_set_fd(loop_dev.as_raw_fd(), image_file.as_raw_fd() as u64) }.expect("Failed to attach image to loop device");
+
+    // Always auto-clear on last close so the loop device doesn't leak once the jail exits
+    let lo_flags = if readonly { LO_FLAGS_AUTOCLEAR | LO_FLAGS_READ_ONLY } else { LO_FLAGS_AUTOCLEAR };
+    let info = LoopInfo64 { lo_flags, ..Default::default() };
+    unsafe { loop_set_status64(loop_dev.as_raw_fd(), &info) }.expect("Failed to set loop device status");
+
+    loop_path
+}
+
+const COMMON_FS_TYPES: &[&str] = &["ext4", "xfs", "btrfs", "vfat"];
+
+/// Attach `image` as a loop device and mount it at a fresh temp dir under the
+/// current (already unshared) mount namespace, returning that mountpoint
+fn mount_image<T: AsRef<Path>>(image: T, fs_type: Option<&str>, readonly: bool) -> PathBuf {
+    let loop_dev = attach_loop_device(image, readonly);
+
+    // Name the mountpoint after the (host-global, not namespace-local) loop device
+    // we just attached, rather than our own pid, which is always the same small
+    // number this soon after unsharing a fresh PID namespace.
+    let loop_name = loop_dev.file_name().expect("Loop device path has no file name");
+    let mountpoint = std::env::temp_dir().join(format!("choot-image-{}", loop_name.to_string_lossy()));
+    std::fs::create_dir_all(&mountpoint).expect("Failed to create image mountpoint");
+
+    let flags = if readonly { MsFlags::MS_RDONLY } else { MsFlags::empty() };
+    match fs_type {
+        Some(fs_type) => mount(Some(&loop_dev), &mountpoint, Some(fs_type), flags, None::<&Path>).expect("Failed to mount image"),
+        None => {
+            let mounted = COMMON_FS_TYPES.iter()
+                .any(|fs_type| mount(Some(&loop_dev), &mountpoint, Some(*fs_type), flags, None::<&Path>).is_ok());
+            assert!(mounted, "Failed to autodetect filesystem type for image");
+        },
+    }
+
+    mountpoint
+}
+
 /// Setup filesystem mounts
-fn setup_mounts<T: AsRef<Path>>(target: T, readonly: bool) {
+fn setup_mounts<T: AsRef<Path>>(target: T, readonly: bool, propagation: MsFlags, overlay: Option<&OverlaySpec>, binds: &[BindMount], tmpfs_mounts: &[TmpfsMount], rootless: bool) {
     let target = target.as_ref();
 
-    make_rslave("/").expect("Failed to mark root rslave");
-    bind_mount(target, target).expect("Failed to bind-mount root");
+    set_propagation("/", propagation).expect("Failed to set root propagation");
+    match overlay {
+        Some(overlay) => setup_overlay(target, overlay).expect("Failed to mount overlay root"),
+        None => bind_mount(target, target).expect("Failed to bind-mount root"),
+    }
+
+    // Binds/tmpfs must land before the readonly remount below, since creating their
+    // destination directories would otherwise fail with EROFS
+    setup_binds(target, binds, tmpfs_mounts);
 
     if readonly {
         remount_readonly(target).expect("Failed to remount readonly");
     }
 
     mount_special(target.join("proc"), "proc", MsFlags::empty(), None).expect("Failed to mount proc");
-    mount_special(target.join("sys"), "sysfs", MsFlags::empty(), None).expect("Failed to mount sysfs");
+    if rootless {
+        // Mounting a fresh sysfs instance requires owning the network namespace too, which
+        // we don't unshare, so bind-mount the host's /sys instead (mirrors the device fallback).
+        bind_mount("/sys", target.join("sys")).expect("Failed to bind-mount /sys");
+    } else {
+        mount_special(target.join("sys"), "sysfs", MsFlags::empty(), None).expect("Failed to mount sysfs");
+    }
     mount_special(target.join("dev"), "tmpfs", MsFlags::MS_NOSUID | MsFlags::MS_STRICTATIME, Some("mode=755")).expect("Failed to mount dev tmpfs");
 }
 
+/// An `--overlay`/`--lower` spec for mounting ROOT as an overlayfs
+struct OverlaySpec {
+    lowers: Vec<PathBuf>,
+    upper_work: Option<(PathBuf, PathBuf)>,
+}
+
+fn parse_overlay_upper_work(spec: &str) -> (PathBuf, PathBuf) {
+    let mut parts = spec.splitn(2, ':');
+    let upper = parts.next().unwrap_or_else(|| panic!("Invalid --overlay spec: {}", spec));
+    let work = parts.next().unwrap_or_else(|| panic!("Invalid --overlay spec: {}", spec));
+
+    (PathBuf::from(upper), PathBuf::from(work))
+}
+
+/// Mount `target` itself as an overlayfs, with `target`'s current contents as the
+/// top lower layer, above any `--lower` dirs. Falls back to a read-only overlay when no upper/work dir is given.
+fn setup_overlay<T: AsRef<Path>>(target: T, overlay: &OverlaySpec) -> nix::Result<()> {
+    let target = target.as_ref();
+
+    let mut lowerdirs = vec![target.display().to_string()];
+    lowerdirs.extend(overlay.lowers.iter().map(|lower| lower.display().to_string()));
+    let lowerdir = lowerdirs.join(":");
+
+    let data = match &overlay.upper_work {
+        Some((upper, work)) => format!("lowerdir={},upperdir={},workdir={}", lowerdir, upper.display(), work.display()),
+        None => format!("lowerdir={}", lowerdir),
+    };
+
+    mount(Some("overlay"), target, Some("overlay"), MsFlags::empty(), Some(data.as_str()))
+}
+
+/// A `--bind SRC:DST[:ro]` spec
+struct BindMount {
+    source: PathBuf,
+    dest: PathBuf,
+    readonly: bool,
+}
+
+/// A `--tmpfs DST[:opts]` spec
+struct TmpfsMount {
+    dest: PathBuf,
+    opts: Option<String>,
+}
+
+fn parse_bind_spec(spec: &str) -> BindMount {
+    let mut parts = spec.splitn(3, ':');
+    let source = parts.next().unwrap_or_else(|| panic!("Invalid --bind spec: {}", spec));
+    let dest = parts.next().unwrap_or_else(|| panic!("Invalid --bind spec: {}", spec));
+    let readonly = matches!(parts.next(), Some("ro"));
+
+    BindMount { source: PathBuf::from(source), dest: PathBuf::from(dest), readonly }
+}
+
+fn parse_tmpfs_spec(spec: &str) -> TmpfsMount {
+    let mut parts = spec.splitn(2, ':');
+    let dest = parts.next().unwrap_or_else(|| panic!("Invalid --tmpfs spec: {}", spec));
+
+    TmpfsMount { dest: PathBuf::from(dest), opts: parts.next().map(String::from) }
+}
+
+/// Resolve `dest` relative to `target`, rejecting any path with a `..` component.
+/// The check is lexical only: symlinks already present under `target` are followed.
+fn resolve_under<T: AsRef<Path>, U: AsRef<Path>>(target: T, dest: U) -> PathBuf {
+    let target = target.as_ref();
+    let dest = dest.as_ref();
+    if dest.components().any(|c| c == std::path::Component::ParentDir) {
+        panic!("Mount destination escapes target: {}", dest.display());
+    }
+
+    target.join(dest.strip_prefix("/").unwrap_or(dest))
+}
+
+/// Setup host bind mounts and tmpfs volumes requested via `--bind`/`--tmpfs`
+fn setup_binds<T: AsRef<Path>>(target: T, binds: &[BindMount], tmpfs_mounts: &[TmpfsMount]) {
+    let target = target.as_ref();
+
+    for bind in binds {
+        let dest = resolve_under(target, &bind.dest);
+        std::fs::create_dir_all(&dest).expect("Failed to create bind destination");
+        bind_mount(&bind.source, &dest).expect("Failed to bind-mount");
+        if bind.readonly {
+            remount_readonly(&dest).expect("Failed to remount bind readonly");
+        }
+    }
+
+    for tmpfs in tmpfs_mounts {
+        let dest = resolve_under(target, &tmpfs.dest);
+        std::fs::create_dir_all(&dest).expect("Failed to create tmpfs destination");
+        mount_special(&dest, "tmpfs", MsFlags::empty(), tmpfs.opts.as_deref()).expect("Failed to mount tmpfs");
+    }
+}
+
 /// Setup device nodes
-fn setup_devices<T: AsRef<Path>>(target: T) {
+fn setup_devices<T: AsRef<Path>>(target: T, rootless: bool) {
     let target = target.as_ref();
 
     let dev_mode: Mode = Mode::S_IRUSR | Mode::S_IWUSR | Mode::S_IRGRP | Mode::S_IWGRP | Mode::S_IROTH | Mode::S_IWOTH;
-    make_chardev(target.join("dev/null"), dev_mode, 1, 3).expect("Failed to make /dev/null");
-    make_chardev(target.join("dev/zero"), dev_mode, 1, 5).expect("Failed to make /dev/zero");
-    make_chardev(target.join("dev/full"), dev_mode, 1, 7).expect("Failed to make /dev/full");
-    make_chardev(target.join("dev/random"), dev_mode, 1, 8).expect("Failed to make /dev/random");
-    make_chardev(target.join("dev/urandom"), dev_mode, 1, 9).expect("Failed to make /dev/urandom");
-    make_chardev(target.join("dev/tty"), dev_mode, 5, 0).expect("Failed to make /dev/tty");
-    make_chardev(target.join("dev/ptmx"), dev_mode, 5, 2).expect("Failed to make /dev/ptmx");
+    let devices = [
+        ("dev/null", 1, 3),
+        ("dev/zero", 1, 5),
+        ("dev/full", 1, 7),
+        ("dev/random", 1, 8),
+        ("dev/urandom", 1, 9),
+        ("dev/tty", 5, 0),
+    ];
+    for (path, major, minor) in devices {
+        let dest = target.join(path);
+        if rootless {
+            // mknod requires a privilege unavailable in a user namespace, so bind-mount
+            // the host's device node over an empty placeholder file instead.
+            bind_mount_dev(Path::new("/").join(path), &dest).unwrap_or_else(|_| panic!("Failed to bind-mount {}", path));
+        } else {
+            make_chardev(&dest, dev_mode, major, minor).unwrap_or_else(|_| panic!("Failed to make {}", path));
+        }
+    }
+
+    setup_devpts(target);
 
     symlink("/proc/self/fd", target.join("dev/fd")).expect("Failed to symlink /dev/fd");
     symlink("/proc/self/fd/0", target.join("dev/stdin")).expect("Failed to symlink /dev/stdin");
@@ -99,6 +418,29 @@ fn setup_devices<T: AsRef<Path>>(target: T) {
     symlink("/proc/self/fd/2", target.join("dev/stderr")).expect("Failed to symlink /dev/stderr");
 }
 
+/// Mount a private devpts instance and /dev/shm, with `/dev/ptmx` linked to
+/// the instance's own `pts/ptmx`, so pty allocation (`openpty`/`posix_openpt`) works inside the jail
+fn setup_devpts<T: AsRef<Path>>(target: T) {
+    let target = target.as_ref();
+
+    std::fs::create_dir_all(target.join("dev/pts")).expect("Failed to create dev/pts");
+    std::fs::create_dir_all(target.join("dev/shm")).expect("Failed to create dev/shm");
+
+    let mut devpts_opts = String::from("newinstance,ptmxmode=0666,mode=620");
+    if let Some(gid) = tty_gid() {
+        devpts_opts.push_str(&format!(",gid={}", gid));
+    }
+    mount_special(target.join("dev/pts"), "devpts", MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC, Some(&devpts_opts)).expect("Failed to mount devpts");
+    mount_special(target.join("dev/shm"), "tmpfs", MsFlags::MS_NOSUID | MsFlags::MS_NODEV, None).expect("Failed to mount /dev/shm");
+
+    symlink("pts/ptmx", target.join("dev/ptmx")).expect("Failed to symlink /dev/ptmx");
+}
+
+/// Look up the gid of the host's `tty` group, if one exists
+fn tty_gid() -> Option<Gid> {
+    Group::from_name("tty").ok().flatten().map(|group| group.gid)
+}
+
 fn enter_chroot<T: AsRef<Path>, U: AsRef<CStr>>(target: T, args: &[U]) {
     let target = target.as_ref();
     let args: Vec<_> = args.into_iter().map(AsRef::as_ref).collect();
@@ -116,8 +458,20 @@ fn enter_chroot<T: AsRef<Path>, U: AsRef<CStr>>(target: T, args: &[U]) {
     execve(args[0], &args, &env).unwrap();
 }
 
-fn make_rslave<T: AsRef<Path>>(target: T) -> nix::Result<()> {
-    mount(None::<&Path>, target.as_ref(), None::<&Path>, MsFlags::MS_REC | MsFlags::MS_SLAVE, None::<&Path>)
+/// Translate a `--propagation` value into the matching recursive `MsFlags`
+fn propagation_flags(propagation: &str) -> MsFlags {
+    let flag = match propagation {
+        "shared" => MsFlags::MS_SHARED,
+        "private" => MsFlags::MS_PRIVATE,
+        "slave" => MsFlags::MS_SLAVE,
+        "unbindable" => MsFlags::MS_UNBINDABLE,
+        _ => unreachable!("validated by clap possible_values"),
+    };
+    MsFlags::MS_REC | flag
+}
+
+fn set_propagation<T: AsRef<Path>>(target: T, flags: MsFlags) -> nix::Result<()> {
+    mount(None::<&Path>, target.as_ref(), None::<&Path>, flags, None::<&Path>)
 }
 
 fn bind_mount<T: AsRef<Path>, U: AsRef<Path>>(source: T, target: U) -> nix::Result<()> {
@@ -140,6 +494,14 @@ fn make_chardev<T: AsRef<Path>>(target: T, mode: Mode, major: u64, minor: u64) -
     mknod(target.as_ref(), SFlag::S_IFCHR, mode, makedev(major, minor))
 }
 
+/// Bind-mount a host device node onto an empty placeholder file, for use in a
+/// user namespace where `mknod` is not permitted.
+fn bind_mount_dev<T: AsRef<Path>, U: AsRef<Path>>(source: T, target: U) -> nix::Result<()> {
+    let target = target.as_ref();
+    std::fs::File::create(target).expect("Failed to create device placeholder");
+    bind_mount(source, target)
+}
+
 fn symlink<T: AsRef<Path>, U: AsRef<Path>>(target: T, linkpath: U) -> nix::Result<()> {
     symlinkat(target.as_ref(), None, linkpath.as_ref())
 }